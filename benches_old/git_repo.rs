@@ -100,13 +100,13 @@ fn bench_repo(c: &mut Criterion, name: &str, tag2: &str, tag1: &str, num_commits
     }
 }
 
-fn bench_file_diffs<M: Measurement>(
+fn bench_file_diffs<'a, M: Measurement>(
     mut group: BenchmarkGroup<M>,
-    files: &[(Vec<u8>, Vec<u8>, usize)],
+    files: &'a [(Vec<u8>, Vec<u8>, usize)],
     num_chunks: usize,
     compare_to_similar: bool,
 ) {
-    let mut run = |name, f: fn(&[u8], &[u8]) -> usize| {
+    let mut run = |name, f: &mut dyn FnMut(&'a [u8], &'a [u8]) -> usize| {
         let mut i = 0;
         for chunk in files.chunks((files.len() + num_chunks - 1) / num_chunks) {
             let mut average_complexity: usize = chunk.iter().map(|(_, _, it)| *it).sum();
@@ -128,18 +128,25 @@ fn bench_file_diffs<M: Measurement>(
         }
     };
 
-    run("imara_diff-histogram", |file1, file2| {
+    run("imara_diff-histogram", &mut |file1, file2| {
         let input = InternedInput::new(file1, file2);
         imara_diff::diff(Algorithm::Histogram, &input, Counter::default()).total()
     });
 
-    run("imara_diff-myers", |file1, file2| {
+    // reuses the allocations of a single `InternedInput` for all diffs
+    let mut input = InternedInput::default();
+    run("imara_diff-histogram-fill", &mut |file1, file2| {
+        input.fill(file1, file2);
+        imara_diff::diff(Algorithm::Histogram, &input, Counter::default()).total()
+    });
+
+    run("imara_diff-myers", &mut |file1, file2| {
         let input = InternedInput::new(file1, file2);
         imara_diff::diff(Algorithm::Myers, &input, Counter::default()).total()
     });
 
     if compare_to_similar {
-        run("similar", |file1, file2| {
+        run("similar", &mut |file1, file2| {
             let diff = similar::utils::diff_lines(similar::Algorithm::Myers, file1, file2);
             diff.len()
         });
@@ -0,0 +1,84 @@
+use std::ops::Range;
+
+use crate::intern::Token;
+use crate::{diff_with_tokens, Algorithm};
+
+/// A region of `base` that was changed in at least one side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombinedHunk {
+    /// The **position** of the region in `base`.
+    pub base: Range<u32>,
+    /// The corresponding region in each side, in the same order as the `sides` passed to [`combined_diff`].
+    pub sides: Vec<SideHunk>,
+}
+
+/// The region of a side that corresponds to a [`CombinedHunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideHunk {
+    /// The **position** of the region in the side.
+    pub range: Range<u32>,
+    /// Whether the side changed this region.
+    /// If `false` the region is identical to the `base` region of the hunk.
+    pub changed: bool,
+}
+
+/// Computes the combined diff between `base` and each of the `sides` using the specified `algorithm`.
+///
+/// This is the building block for output like git's combined diff
+/// (`@@@ ... @@@`) that is shown for merges.
+/// Changes from different sides that overlap (or touch) in `base` are merged into a single [`CombinedHunk`].
+/// The returned hunks are sorted by their position in `base` and do not overlap.
+pub fn combined_diff(
+    algorithm: Algorithm,
+    base: &[Token],
+    sides: &[&[Token]],
+    num_tokens: u32,
+) -> Vec<CombinedHunk> {
+    let mut changes = Vec::new();
+    for (side, &tokens) in sides.iter().enumerate() {
+        diff_with_tokens(
+            algorithm,
+            base,
+            tokens,
+            num_tokens,
+            |before: Range<u32>, after: Range<u32>| changes.push((side, before, after)),
+        );
+    }
+    // the sort is stable so the changes of each side remain in order
+    changes.sort_by_key(|(_, before, _)| before.start);
+
+    // end of the last change in (`base`, side) for each side,
+    // the unchanged tokens afterwards are at the same offset in both files
+    let mut last_change = vec![(0, 0); sides.len()];
+    let mut hunks = Vec::new();
+    let mut changes = changes.into_iter().peekable();
+    while let Some((_, first, _)) = changes.peek() {
+        let start = first.start;
+        let mut end = first.end;
+        let mut side_hunks: Vec<_> = last_change
+            .iter()
+            .map(|&(base_end, side_end)| {
+                let pos = side_end + (start - base_end);
+                SideHunk {
+                    range: pos..pos,
+                    changed: false,
+                }
+            })
+            .collect();
+        while let Some((side, before, after)) =
+            changes.next_if(|(_, before, _)| before.start <= end)
+        {
+            end = end.max(before.end);
+            last_change[side] = (before.end, after.end);
+            side_hunks[side].changed = true;
+        }
+        for (hunk, &(base_end, side_end)) in side_hunks.iter_mut().zip(&last_change) {
+            hunk.range.end = side_end + (end - base_end);
+        }
+        hunks.push(CombinedHunk {
+            base: start..end,
+            sides: side_hunks,
+        });
+    }
+    hunks
+}
@@ -3,6 +3,7 @@ use std::ops::Range;
 use crate::histogram::lcs::find_lcs;
 use crate::histogram::list_pool::{ListHandle, ListPool};
 use crate::intern::Token;
+use crate::sink::ReportUnchanged;
 use crate::util::{strip_common_postfix, strip_common_prefix};
 use crate::{myers, Sink};
 
@@ -20,8 +21,9 @@ pub fn diff<S: Sink>(
     mut before: &[Token],
     mut after: &[Token],
     num_tokens: u32,
-    mut sink: S,
+    sink: S,
 ) -> S::Out {
+    let mut sink = ReportUnchanged::new(sink, before.len() as u32, after.len() as u32);
     let mut histogram = Histogram::new(num_tokens);
     let prefix = strip_common_prefix(&mut before, &mut after);
     strip_common_postfix(&mut before, &mut after);
@@ -49,6 +51,8 @@ impl Histogram {
         self.token_occurances[token.0 as usize].len(&self.pool)
     }
 
+    /// Records the positions of all tokens in `file`.
+    /// The occurrence list of each token is sorted by position.
     fn populate(&mut self, file: &[Token]) {
         for (i, &token) in file.iter().enumerate() {
             self.token_occurances[token.0 as usize].push(i as u32, &mut self.pool);
@@ -108,6 +108,10 @@ impl LcsSearch {
 
             let len = end2 - start2;
             debug_assert_eq!(len, end1 - start1);
+            // candidates are visited in positional order (`after` is scanned front to back
+            // and the occurrence lists are sorted by position). A candidate replaces the current
+            // LCS if it is longer or its token occurs fewer times, so a candidate with the same
+            // length and the same number of occurrences never replaces an earlier one.
             if self.lcs.len < len || self.min_occurances > occurances {
                 self.min_occurances = occurances;
                 self.lcs = Lcs {
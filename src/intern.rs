@@ -1,8 +1,15 @@
 use std::hash::{BuildHasher as _, Hash};
+use std::mem::swap;
 use std::ops::Index;
 
 use hashbrown::hash_table::{Entry, HashTable};
 use hashbrown::DefaultHashBuilder as RandomState;
+#[cfg(feature = "serde")]
+use serde::de::Error as _;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::sources::{by_key, Keyed};
 
 /// A token represented as an interned integer.
 ///
@@ -13,10 +20,30 @@ use hashbrown::DefaultHashBuilder as RandomState;
 /// This allows for much better performance by amortizing the cost hashing/equality.
 ///
 /// While you can intern tokens yourself it is strongly recommended to use [`InternedInput`] module.
+///
+/// A token is only meaningful together with the [`Interner`] that produced it:
+/// its id is always smaller than [`Interner::num_tokens`].
+/// Indexing an interner or diffing with a token outside of that range panics
+/// (see [`try_diff_with_tokens`](crate::try_diff_with_tokens) for a checked alternative).
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 #[repr(transparent)]
 pub struct Token(pub u32);
 
+impl Token {
+    /// Creates a token with the given `id`.
+    ///
+    /// The id must be smaller than the [`num_tokens`](Interner::num_tokens)
+    /// of the interner the token is used with.
+    pub const fn new(id: u32) -> Token {
+        Token(id)
+    }
+
+    /// Returns the id of this token.
+    pub const fn id(self) -> u32 {
+        self.0
+    }
+}
+
 impl From<u32> for Token {
     fn from(token: u32) -> Self {
         Token(token)
@@ -29,6 +56,20 @@ impl From<Token> for u32 {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Token {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(Token)
+    }
+}
+
 pub trait TokenSource {
     type Token: Hash + Eq;
     type Tokenizer: Iterator<Item = Self::Token>;
@@ -58,9 +99,39 @@ impl<T> InternedInput<T> {
         self.after.clear();
         self.interner.clear();
     }
+
+    /// Swaps `before` and `after`, so that subsequent diffs compute the
+    /// edit-script in the reverse direction. The interner is shared by both sides and is not affected.
+    pub fn swap_sides(&mut self) {
+        swap(&mut self.before, &mut self.after);
+    }
+
+    /// Returns `true` if `before` and `after` contain exactly the same tokens,
+    /// which means that the diff between them is empty.
+    pub fn sides_equal(&self) -> bool {
+        self.before == self.after
+    }
 }
 
 impl<T: Eq + Hash> InternedInput<T> {
+    /// Tokenizes and interns `before` and `after`.
+    ///
+    /// Tokens are numbered in the order in which they first appear when
+    /// scanning `before` and then `after`.
+    /// The resulting token ids are therefore reproducible for a given input.
+    ///
+    /// `&str` and `&[u8]` implement [`TokenSource`] directly (producing a line diff),
+    /// so no wrappers are needed to construct inputs from strings or bytes:
+    ///
+    /// ```
+    /// use imara_diff::intern::InternedInput;
+    ///
+    /// let input = InternedInput::new("foo\nbar\n", "foo\nbaz\n");
+    /// assert_eq!(input.interner[input.after[1]], "baz");
+    ///
+    /// let input = InternedInput::new(&b"foo\nbar\n"[..], &b"foo\nbaz\n"[..]);
+    /// assert_eq!(input.interner[input.after[1]], b"baz");
+    /// ```
     pub fn new<I: TokenSource<Token = T>>(before: I, after: I) -> Self {
         let token_estimate_before = before.estimate_tokens() as usize;
         let token_estimate_after = after.estimate_tokens() as usize;
@@ -74,6 +145,19 @@ impl<T: Eq + Hash> InternedInput<T> {
         res
     }
 
+    /// Replaces the contents of `self` with the interned tokens of `before` and `after`.
+    ///
+    /// The result is the same as calling [`new`](crate::intern::InternedInput::new)
+    /// but the existing allocations are reused.
+    /// This is useful for computing many diffs in a loop.
+    pub fn fill<I: TokenSource<Token = T>>(&mut self, before: I, after: I) {
+        self.clear();
+        self.before.reserve(before.estimate_tokens() as usize);
+        self.after.reserve(after.estimate_tokens() as usize);
+        self.update_before(before.tokenize());
+        self.update_after(after.tokenize());
+    }
+
     /// replaces `self.before` wtih the iterned Tokens yielded by `input`
     /// Note that this does not erase any tokens from the interner and might therefore be considered
     /// a memory leak. If this function is called often over a long_running process
@@ -96,12 +180,28 @@ impl<T: Eq + Hash> InternedInput<T> {
     }
 }
 
+impl<T, K: Eq + Hash> InternedInput<Keyed<T, K>> {
+    /// Tokenizes and interns `before` and `after` but compares tokens by the key
+    /// returned by `key` instead of the tokens themselves.
+    ///
+    /// This is a shorthand for wrapping both sources with [`by_key`].
+    /// The interner stores the original tokens so they can still be displayed.
+    pub fn new_with_key<I, F>(before: I, after: I, key: F) -> Self
+    where
+        I: TokenSource<Token = T>,
+        F: Fn(&T) -> K + Clone,
+    {
+        Self::new(by_key(before, key.clone()), by_key(after, key))
+    }
+}
+
 /// An interner that allows for fast access of tokens produced by a [`TokenSource`].
 #[derive(Default)]
 pub struct Interner<T> {
     tokens: Vec<T>,
     table: HashTable<Token>,
     hasher: RandomState,
+    lru: Option<Lru>,
 }
 
 impl<T> Interner<T> {
@@ -117,6 +217,33 @@ impl<T> Interner<T> {
             tokens: Vec::with_capacity(capacity),
             table: HashTable::with_capacity(capacity),
             hasher: RandomState::default(),
+            lru: None,
+        }
+    }
+
+    /// Create an Interner that never holds more than `max_tokens` distinct tokens.
+    ///
+    /// Once `max_tokens` tokens are interned, interning a new token evicts the token
+    /// that was least recently passed to [`intern`](crate::intern::Interner::intern)
+    /// and reuses its id.
+    /// This bounds the memory used by long-running processes that diff a stream of files.
+    ///
+    /// Any [`Token`] that is still stored elsewhere (for example in the `before`/`after` fields
+    /// of an [`InternedInput`]) silently refers to a different token after it was evicted.
+    /// This interner should therefore only be used if all tokens are interned again for each diff
+    /// and `max_tokens` is larger than the number of distinct tokens in any single diff.
+    /// In that case the tokens of the current diff are never evicted.
+    /// Because ids are reused, [`erase_tokens_after`](crate::intern::Interner::erase_tokens_after)
+    /// can not be used with this interner, use [`clear`](crate::intern::Interner::clear) instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_tokens` is zero.
+    pub fn with_max_tokens(max_tokens: u32) -> Interner<T> {
+        assert!(max_tokens != 0, "max_tokens must be non-zero");
+        Interner {
+            lru: Some(Lru::new(max_tokens)),
+            ..Interner::new(0)
         }
     }
 
@@ -124,6 +251,9 @@ impl<T> Interner<T> {
     pub fn clear(&mut self) {
         self.table.clear();
         self.tokens.clear();
+        if let Some(lru) = &mut self.lru {
+            lru.clear();
+        }
     }
 
     /// Returns to total number of **distinct** tokens currently interned.
@@ -134,8 +264,16 @@ impl<T> Interner<T> {
 
 impl<T: Hash + Eq> Interner<T> {
     /// Intern `token` and return a the interned integer.
+    ///
+    /// Distinct tokens receive consecutive ids starting at `0` in the order they
+    /// are first interned. Interning a token that was seen before returns its existing id.
+    /// If the interner was created with [`with_max_tokens`](crate::intern::Interner::with_max_tokens),
+    /// new tokens may instead reuse the id of an evicted token.
     pub fn intern(&mut self, token: T) -> Token {
         let hash = self.hasher.hash_one(&token);
+        if self.lru.is_some() {
+            return self.intern_lru(token, hash);
+        }
         match self.table.entry(
             hash,
             |&it| self.tokens[it.0 as usize] == token,
@@ -151,8 +289,67 @@ impl<T: Hash + Eq> Interner<T> {
         }
     }
 
+    fn intern_lru(&mut self, token: T, hash: u64) -> Token {
+        let Interner {
+            tokens,
+            table,
+            hasher,
+            lru,
+        } = self;
+        let lru = lru.as_mut().unwrap();
+        if let Some(&interned) = table.find(hash, |&it| tokens[it.0 as usize] == token) {
+            lru.touch(interned.0);
+            return interned;
+        }
+
+        let id = if tokens.len() as u32 >= lru.max_tokens {
+            let evicted = lru.tail;
+            lru.unlink(evicted);
+            let evicted_hash = hasher.hash_one(&tokens[evicted as usize]);
+            match table.find_entry(evicted_hash, |token| token.0 == evicted) {
+                Ok(occupied) => drop(occupied.remove()),
+                Err(_absent) => unreachable!(),
+            }
+            tokens[evicted as usize] = token;
+            evicted
+        } else {
+            tokens.push(token);
+            lru.push();
+            tokens.len() as u32 - 1
+        };
+        lru.push_front(id);
+        table.insert_unique(hash, Token(id), |&token| {
+            hasher.hash_one(&tokens[token.0 as usize])
+        });
+        Token(id)
+    }
+
+    /// Interns all tokens of `self` into `other` and returns the resulting mapping.
+    ///
+    /// The token at index `i` of the returned `Vec` is the token in `other` that corresponds to `Token(i)`
+    /// in `self`. This allows relating tokens that were interned with different interners.
+    pub fn remap_into(&self, other: &mut Interner<T>) -> Vec<Token>
+    where
+        T: Clone,
+    {
+        self.tokens
+            .iter()
+            .map(|token| other.intern(token.clone()))
+            .collect()
+    }
+
     /// Erases `first_erased_token` and any tokens interned afterward from the interner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interner was created with [`with_max_tokens`](crate::intern::Interner::with_max_tokens).
+    /// Such an interner reuses the ids of evicted tokens, so tokens interned after `first_erased_token`
+    /// do not necessarily have a larger id.
     pub fn erase_tokens_after(&mut self, first_erased_token: Token) {
+        assert!(
+            self.lru.is_none(),
+            "erase_tokens_after is not supported for interners with a token limit"
+        );
         assert!(first_erased_token.0 <= self.tokens.len() as u32);
         let retained = first_erased_token.0 as usize;
         let erased = self.tokens.len() - retained;
@@ -186,3 +383,104 @@ impl<T> Index<Token> for Interner<T> {
         &self.tokens[index.0 as usize]
     }
 }
+
+/// An interner is serialized as the list of its tokens ordered by id.
+///
+/// Deserializing re-interns the tokens in the same order, so every token keeps its id.
+/// This allows caching tokens (and diffs computed from them) across process restarts.
+/// The token limit of an interner created with
+/// [`with_max_tokens`](crate::intern::Interner::with_max_tokens) is not persisted.
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for Interner<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.tokens.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de> + Hash + Eq> Deserialize<'de> for Interner<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tokens = Vec::<T>::deserialize(deserializer)?;
+        let mut interner = Interner::new(tokens.len());
+        for token in tokens {
+            let id = interner.num_tokens();
+            if interner.intern(token).0 != id {
+                return Err(D::Error::custom("duplicate token in interner"));
+            }
+        }
+        Ok(interner)
+    }
+}
+
+const NIL: u32 = u32::MAX;
+
+/// A doubly linked list of token ids ordered by the time they were last interned.
+/// Used by [`Interner::with_max_tokens`] to find the least recently used token in constant time.
+#[derive(Debug)]
+struct Lru {
+    max_tokens: u32,
+    prev: Vec<u32>,
+    next: Vec<u32>,
+    /// most recently used token
+    head: u32,
+    /// least recently used token
+    tail: u32,
+}
+
+impl Lru {
+    fn new(max_tokens: u32) -> Lru {
+        Lru {
+            max_tokens,
+            prev: Vec::new(),
+            next: Vec::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.prev.clear();
+        self.next.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    /// Allocates a list node for a newly interned token.
+    fn push(&mut self) {
+        self.prev.push(NIL);
+        self.next.push(NIL);
+    }
+
+    fn unlink(&mut self, id: u32) {
+        let prev = self.prev[id as usize];
+        let next = self.next[id as usize];
+        if prev == NIL {
+            self.head = next;
+        } else {
+            self.next[prev as usize] = next;
+        }
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            self.prev[next as usize] = prev;
+        }
+    }
+
+    fn push_front(&mut self, id: u32) {
+        self.prev[id as usize] = NIL;
+        self.next[id as usize] = self.head;
+        if self.head == NIL {
+            self.tail = id;
+        } else {
+            self.prev[self.head as usize] = id;
+        }
+        self.head = id;
+    }
+
+    fn touch(&mut self, id: u32) {
+        if self.head != id {
+            self.unlink(id);
+            self.push_front(id);
+        }
+    }
+}
@@ -149,11 +149,17 @@
 //! assert_eq!(changes.removals, 1);
 //! ```
 
+pub use combined::{combined_diff, CombinedHunk, SideHunk};
 #[cfg(feature = "unified_diff")]
 pub use unified_diff::UnifiedDiffBuilder;
+pub use windowed::WindowedDiff;
+
+use std::error::Error;
+use std::fmt::{self, Display};
 
 use crate::intern::{InternedInput, Token, TokenSource};
 pub use crate::sink::Sink;
+mod combined;
 mod histogram;
 pub mod intern;
 mod myers;
@@ -161,7 +167,8 @@ pub mod sink;
 pub mod sources;
 #[cfg(feature = "unified_diff")]
 mod unified_diff;
-mod util;
+pub mod util;
+mod windowed;
 
 #[cfg(test)]
 mod tests;
@@ -221,6 +228,9 @@ pub enum Algorithm {
     /// Same as `Myers` but the early abort heuristics are disabled to guarantee
     /// a minimal edit sequence.
     /// This can mean significant slowdown in pathological cases.
+    ///
+    /// The edit sequence produced by `MyersMinimal` is never longer than the
+    /// edit sequence produced by any other algorithm for the same input.
     MyersMinimal,
 }
 
@@ -233,7 +243,8 @@ impl Algorithm {
 /// the specified `algorithm`
 /// The edit-script is passed to `sink.process_change` while it is produced.
 pub fn diff<S: Sink, T>(algorithm: Algorithm, input: &InternedInput<T>, sink: S) -> S::Out {
-    diff_with_tokens(
+    // the interner only hands out tokens smaller than `num_tokens`
+    diff_unchecked(
         algorithm,
         &input.before,
         &input.after,
@@ -245,12 +256,38 @@ pub fn diff<S: Sink, T>(algorithm: Algorithm, input: &InternedInput<T>, sink: S)
 /// Computes an edit-script that transforms `before` into `after` using
 /// the specified `algorithm`
 /// The edit-script is passed to `sink.process_change` while it is produced.
+///
+/// All tokens in `before` and `after` should be smaller than `num_tokens`.
+/// Larger tokens are still handled correctly, but can make the diff
+/// considerably slower and use more memory.
+/// Use [`try_diff_with_tokens`] to reject them instead.
 pub fn diff_with_tokens<S: Sink>(
     algorithm: Algorithm,
     before: &[Token],
     after: &[Token],
     num_tokens: u32,
     sink: S,
+) -> S::Out {
+    // the algorithms index tables by token id, so tokens that are not smaller than `num_tokens`
+    // would index out of bounds (or allocate huge tables), renumber them instead
+    if before
+        .iter()
+        .chain(after)
+        .any(|token| token.0 >= num_tokens)
+    {
+        let (before, after, num_tokens) = util::renumber(before, after);
+        return diff_unchecked(algorithm, &before, &after, num_tokens, sink);
+    }
+    diff_unchecked(algorithm, before, after, num_tokens, sink)
+}
+
+/// Same as [`diff_with_tokens`] but requires all tokens to be smaller than `num_tokens`.
+fn diff_unchecked<S: Sink>(
+    algorithm: Algorithm,
+    before: &[Token],
+    after: &[Token],
+    num_tokens: u32,
+    sink: S,
 ) -> S::Out {
     assert!(
         before.len() < i32::MAX as usize,
@@ -268,3 +305,64 @@ pub fn diff_with_tokens<S: Sink>(
         Algorithm::MyersMinimal => myers::diff(before, after, num_tokens, sink, true),
     }
 }
+
+/// Same as [`diff_with_tokens`] but first checks that all tokens in `before`
+/// and `after` are smaller than `num_tokens`.
+/// If a token is out of range no diff is computed and an error is returned instead.
+pub fn try_diff_with_tokens<S: Sink>(
+    algorithm: Algorithm,
+    before: &[Token],
+    after: &[Token],
+    num_tokens: u32,
+    sink: S,
+) -> Result<S::Out, TokenOutOfRange> {
+    if let Some(&token) = before
+        .iter()
+        .chain(after)
+        .find(|token| token.0 >= num_tokens)
+    {
+        return Err(TokenOutOfRange { token, num_tokens });
+    }
+    Ok(diff_unchecked(algorithm, before, after, num_tokens, sink))
+}
+
+/// Returns `true` if `before` and `after` consist of the same tokens.
+///
+/// This is much faster than computing a diff and checking whether it is empty:
+/// the tokens are compared directly without interning them and
+/// the comparison stops at the first token that differs.
+///
+/// ```
+/// use imara_diff::are_equal;
+///
+/// assert!(are_equal("foo\nbar\n", "foo\nbar\n"));
+/// assert!(!are_equal("foo\nbar\n", "foo\nbaz\n"));
+/// ```
+pub fn are_equal<I>(before: I, after: I) -> bool
+where
+    I: TokenSource,
+    I::Token: Eq,
+{
+    before.tokenize().eq(after.tokenize())
+}
+
+/// Error returned by [`try_diff_with_tokens`] if a token is not smaller than `num_tokens`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TokenOutOfRange {
+    /// The first token that was not smaller than `num_tokens`.
+    pub token: Token,
+    /// The number of tokens that was passed to [`try_diff_with_tokens`].
+    pub num_tokens: u32,
+}
+
+impl Display for TokenOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "token {} is out of range for {} tokens",
+            self.token.0, self.num_tokens
+        )
+    }
+}
+
+impl Error for TokenOutOfRange {}
@@ -4,6 +4,7 @@ use crate::intern::Token;
 use crate::myers::middle_snake::{MiddleSnakeSearch, SearchResult};
 use crate::myers::preprocess::PreprocessedFile;
 use crate::myers::slice::FileSlice;
+use crate::sink::ReportUnchanged;
 use crate::util::sqrt;
 use crate::Sink;
 
@@ -22,13 +23,14 @@ pub fn diff<S: Sink>(
     before: &[Token],
     after: &[Token],
     _num_tokens: u32,
-    mut sink: S,
+    sink: S,
     minimal: bool,
 ) -> S::Out {
+    let mut sink = ReportUnchanged::new(sink, before.len() as u32, after.len() as u32);
     // preprocess the files by removing parts of the file that are not contained in the other file at all
     // this process remaps the token indices and therefore requires us to track changed files in a char array
     // PERF use a bitset?
-    let (mut before, mut after) = preprocess::preprocess(before, after);
+    let (mut before, mut after) = preprocess::preprocess(before, after, minimal);
 
     // Perform the actual diff
     Myers::new(before.tokens.len(), after.tokens.len()).run(
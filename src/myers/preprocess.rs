@@ -5,22 +5,35 @@ use crate::util::{strip_common_postfix, strip_common_prefix};
 pub fn preprocess(
     mut file1: &[Token],
     mut file2: &[Token],
+    minimal: bool,
 ) -> (PreprocessedFile, PreprocessedFile) {
     let common_prefix = strip_common_prefix(&mut file1, &mut file2);
     strip_common_postfix(&mut file1, &mut file2);
-    let (hdiff1, hdiff2) = token_occurrences(file1, file2);
+    let (hdiff1, hdiff2) = token_occurrences(file1, file2, minimal);
     let file1 = PreprocessedFile::new(common_prefix, &hdiff1, file1);
     let file2 = PreprocessedFile::new(common_prefix, &hdiff2, file2);
     (file1, file2)
 }
 
 /// computes how
-fn token_occurrences(file1: &[Token], file2: &[Token]) -> (Vec<Occurances>, Vec<Occurances>) {
+fn token_occurrences(
+    file1: &[Token],
+    file2: &[Token],
+    minimal: bool,
+) -> (Vec<Occurances>, Vec<Occurances>) {
     const MAX_EQLIMIT: u32 = 1024;
 
     // compute the limit after which tokens are treated as `Occurances::COMMON`
-    let eqlimit1 = sqrt(file1.len()).min(MAX_EQLIMIT);
-    let eqlimit2 = sqrt(file2.len()).min(MAX_EQLIMIT);
+    // common tokens may be pruned which can produce a non-minimal diff,
+    // so (just like git) never treat tokens as common if a minimal diff is required
+    let (eqlimit1, eqlimit2) = if minimal {
+        (u32::MAX, u32::MAX)
+    } else {
+        (
+            sqrt(file1.len()).min(MAX_EQLIMIT),
+            sqrt(file2.len()).min(MAX_EQLIMIT),
+        )
+    };
 
     // first collect how often each token occurs in a file
     let mut occurances1 = Vec::new();
@@ -1,6 +1,31 @@
+//! Processing of the edit-scripts produced by the diff [algorithms](crate::Algorithm).
+//!
+//! Every diff computed with [`diff`](crate::diff) is passed to a [`Sink`] while it is produced.
+//! This module contains the [`Sink`] trait and some general purpose sinks like [`Counter`].
+
 use std::ops::Range;
 
 /// Trait for processing the edit-scripts computed with [`diff`](crate::diff)
+///
+/// `Sink` is implemented for closures that accept the `before` and `after`
+/// ranges of each change, which makes simple sinks easy to write:
+///
+/// ```
+/// use std::ops::Range;
+///
+/// use imara_diff::intern::InternedInput;
+/// use imara_diff::{diff, Algorithm};
+///
+/// let input = InternedInput::new("a\nb\nc\nd\n", "a\nx\nc\n");
+/// let mut changes = 0;
+/// let mut removed = 0;
+/// diff(Algorithm::Histogram, &input, |before: Range<u32>, _after: Range<u32>| {
+///     changes += 1;
+///     removed += before.len();
+/// });
+/// assert_eq!(changes, 2);
+/// assert_eq!(removed, 2);
+/// ```
 pub trait Sink: Sized {
     type Out;
 
@@ -24,13 +49,24 @@ pub trait Sink: Sized {
     ///
     /// # Notes
     ////
-    /// A `Sink` has no function to indicate that a section of a file remains unchanged.
-    /// However due to the monotonically increasing calls, implementations can easily determine
+    /// Due to the monotonically increasing calls, implementations can easily determine
     /// which subsequences remain unchanged by saving `before.end`/`after.end`.
     /// The range between `before.start`/`after.end` and the previous `before.end`/`after.end`
-    /// is always unchanged.
+    /// is always unchanged. Sinks that prefer to be told about these sections explicitly
+    /// can implement [`process_unchanged`](crate::Sink::process_unchanged) instead.
     fn process_change(&mut self, before: Range<u32>, after: Range<u32>);
 
+    /// This method is called for every section of the two files that remains unchanged.
+    ///
+    /// Calls to this function are interleaved with the calls to [`process_change`](crate::Sink::process_change)
+    /// in the same monotonically increasing order, so that the unchanged sections and the changes together
+    /// cover both files entirely. `before` and `after` always have the same (non-zero) length.
+    ///
+    /// The default implementation does nothing.
+    fn process_unchanged(&mut self, before: Range<u32>, after: Range<u32>) {
+        let _ = (before, after);
+    }
+
     /// This function is called after all calls to `process_change` are complete
     /// to obtain the final diff result
     fn finish(self) -> Self::Out;
@@ -61,8 +97,8 @@ impl Sink for () {
 /// A [`Sink`] which wraps a different sink
 /// and counts the number of `removed` and `inserted` [tokens](crate::intern::Token).
 pub struct Counter<T> {
-    /// Total number of recorded inserted [`tokens`](crate::intern::Token).
-    /// Computed by summing the lengths of the `after` subsequences pass to [`process_change`](crate::Sink::process_change).
+    /// Total number of recorded removed [`tokens`](crate::intern::Token).
+    /// Computed by summing the lengths of the `before` subsequences pass to [`process_change`](crate::Sink::process_change).
     pub removals: u32,
     /// Total number of recorded inserted [`tokens`](crate::intern::Token).
     /// Computed by summing the lengths of the `after` subsequences pass to [`process_change`](crate::Sink::process_change).
@@ -75,6 +111,7 @@ pub struct Counter<T> {
 }
 
 impl<S: Sink> Counter<S> {
+    /// Create a new `Counter` that forwards all changes to `sink`.
     pub fn new(sink: S) -> Self {
         Self {
             insertions: 0,
@@ -92,6 +129,10 @@ impl<S: Sink> Sink for Counter<S> {
         self.wrapped.process_change(before, after)
     }
 
+    fn process_unchanged(&mut self, before: Range<u32>, after: Range<u32>) {
+        self.wrapped.process_unchanged(before, after)
+    }
+
     fn finish(self) -> Self::Out {
         Counter {
             removals: self.removals,
@@ -102,6 +143,7 @@ impl<S: Sink> Sink for Counter<S> {
 }
 
 impl<T> Counter<T> {
+    /// The total number of inserted and removed [`tokens`](crate::intern::Token).
     pub fn total(&self) -> usize {
         self.insertions as usize + self.removals as usize
     }
@@ -112,3 +154,105 @@ impl Default for Counter<()> {
         Counter::new(())
     }
 }
+
+/// Wraps a [`Sink`] and reports the unchanged sections between the changes
+/// to [`process_unchanged`](crate::Sink::process_unchanged).
+/// Used by the diff algorithms so that they only have to emit changes.
+pub(crate) struct ReportUnchanged<S> {
+    sink: S,
+    before_pos: u32,
+    after_pos: u32,
+    before_len: u32,
+    after_len: u32,
+}
+
+impl<S: Sink> ReportUnchanged<S> {
+    pub(crate) fn new(sink: S, before_len: u32, after_len: u32) -> Self {
+        Self {
+            sink,
+            before_pos: 0,
+            after_pos: 0,
+            before_len,
+            after_len,
+        }
+    }
+
+    fn unchanged_until(&mut self, before_end: u32, after_end: u32) {
+        if self.before_pos != before_end {
+            self.sink
+                .process_unchanged(self.before_pos..before_end, self.after_pos..after_end);
+        }
+    }
+}
+
+impl<S: Sink> Sink for ReportUnchanged<S> {
+    type Out = S::Out;
+
+    fn process_change(&mut self, before: Range<u32>, after: Range<u32>) {
+        self.unchanged_until(before.start, after.start);
+        self.before_pos = before.end;
+        self.after_pos = after.end;
+        self.sink.process_change(before, after)
+    }
+
+    fn finish(mut self) -> Self::Out {
+        self.unchanged_until(self.before_len, self.after_len);
+        self.sink.finish()
+    }
+}
+
+/// A section of the files compared by [`SpanSink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Span {
+    /// Tokens that are identical in both files.
+    /// Both ranges always have the same length.
+    Keep {
+        before: Range<u32>,
+        after: Range<u32>,
+    },
+    /// Tokens from the `after` file that are not present in the `before` file.
+    Insert(Range<u32>),
+    /// Tokens from the `before` file that are not present in the `after` file.
+    Delete(Range<u32>),
+}
+
+/// A [`Sink`] that splits both files into a list of [`Span`]s.
+///
+/// Besides the changes passed to [`process_change`](crate::Sink::process_change) the
+/// returned spans also include the unchanged sections passed to
+/// [`process_unchanged`](crate::Sink::process_unchanged), so that they cover both files entirely.
+/// The [`Keep`](Span::Keep) and [`Delete`](Span::Delete) spans tile the `before` file
+/// while the [`Keep`](Span::Keep) and [`Insert`](Span::Insert) spans tile the `after` file.
+/// A `Delete` span is always emitted before an `Insert` span at the same position.
+#[derive(Default)]
+pub struct SpanSink {
+    spans: Vec<Span>,
+}
+
+impl SpanSink {
+    /// Create a new, empty `SpanSink`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Sink for SpanSink {
+    type Out = Vec<Span>;
+
+    fn process_change(&mut self, before: Range<u32>, after: Range<u32>) {
+        if !before.is_empty() {
+            self.spans.push(Span::Delete(before));
+        }
+        if !after.is_empty() {
+            self.spans.push(Span::Insert(after));
+        }
+    }
+
+    fn process_unchanged(&mut self, before: Range<u32>, after: Range<u32>) {
+        self.spans.push(Span::Keep { before, after });
+    }
+
+    fn finish(self) -> Self::Out {
+        self.spans
+    }
+}
@@ -1,6 +1,14 @@
+#[cfg(feature = "unicode")]
+use std::borrow::Cow;
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
 use std::mem::take;
+use std::slice::Chunks;
 use std::str::from_utf8_unchecked;
 
+#[cfg(feature = "unicode")]
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
 use crate::TokenSource;
 
 /// Returns a [`TokenSource`] that uses
@@ -21,25 +29,97 @@ pub fn lines_with_terminator(data: &str) -> Lines<'_, true> {
     Lines(ByteLines(data.as_bytes()))
 }
 
+/// Returns a [`TokenSource`] that uses the lines in `data` as tokens
+/// but compares them after normalizing them to Unicode [NFC].
+///
+/// Canonically equivalent lines (for example a precomposed `é` and an `e` followed
+/// by a combining accent) are therefore not detected as changes.
+/// The emitted tokens still contain the original text.
+/// Like [`lines`] the newline seperator is not included in the tokens.
+///
+/// Lines that are already in NFC are compared without allocating, all other
+/// lines are normalized into a newly allocated `String`.
+///
+/// [NFC]: https://unicode.org/reports/tr15/
+#[cfg(feature = "unicode")]
+pub fn lines_nfc<'a>(data: &'a str) -> ByKey<Lines<'a, false>, fn(&&'a str) -> Cow<'a, str>> {
+    by_key(lines(data), nfc)
+}
+
+#[cfg(feature = "unicode")]
+fn nfc<'a>(line: &&'a str) -> Cow<'a, str> {
+    match is_nfc_quick(line.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(line),
+        IsNormalized::No | IsNormalized::Maybe => Cow::Owned(line.nfc().collect()),
+    }
+}
+
 /// Returns a [`TokenSource`] that uses
-/// the lines in `data` as Tokens. A lines is a continous subslice of
-/// `data` which does not contain `\n` (or `\r\n`).
-/// The newline seperator (`\r\n` or `\n`) is not included in the emitted tokens.
+/// the lines in `data` as Tokens. The newline seperator (`\r\n` or `\n`) is
+/// included in the emitted tokens.
 /// This means that changing the newline seperator from `\r\n` to `\n`
-/// (or omitting it fully on the last line) is not detected by [`diff`](crate::diff).
+/// (or omitting it fully on the last line) is  detected by [`diff`](crate::diff).
 pub fn byte_lines_with_terminator(data: &[u8]) -> ByteLines<'_, true> {
     ByteLines(data)
 }
 
 /// Returns a [`TokenSource`] that uses
-/// the lines in `data` as Tokens. The newline seperator (`\r\n` or `\n`) is
-/// included in the emitted tokens.
+/// the lines in `data` as Tokens. A lines is a continous subslice of
+/// `data` which does not contain `\n` (or `\r\n`).
+/// The newline seperator (`\r\n` or `\n`) is not included in the emitted tokens.
 /// This means that changing the newline seperator from `\r\n` to `\n`
-/// (or omitting it fully on the last line) is  detected by [`diff`](crate::diff).
+/// (or omitting it fully on the last line) is not detected by [`diff`](crate::diff).
 pub fn byte_lines(data: &[u8]) -> ByteLines<'_, false> {
     ByteLines(data)
 }
 
+/// Returns a [`TokenSource`] that uses non-overlapping chunks of `size` bytes
+/// from `data` as tokens. The last chunk is shorter if the length of `data` is not
+/// divisible by `size`.
+///
+/// This allows diffing binary data at block granularity.
+///
+/// # Panics
+///
+/// Panics if `size` is zero.
+pub fn byte_chunks(data: &[u8], size: usize) -> ByteChunks<'_> {
+    assert!(size != 0, "chunk size must be non-zero");
+    ByteChunks { data, size }
+}
+
+/// Returns a [`TokenSource`] that uses the fields of `data` separated by `sep` as tokens.
+/// The separator is not included in the emitted tokens.
+///
+/// Empty fields (two consecutive separators) produce empty tokens,
+/// while a trailing separator does not start a new field (just like a trailing newline for [`lines`]).
+///
+/// ```
+/// use imara_diff::sources::split_by;
+///
+/// let fields: Vec<_> = split_by("a,,b,", ",").collect();
+/// assert_eq!(fields, ["a", "", "b"]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `sep` is empty.
+pub fn split_by<'a>(data: &'a str, sep: &'a str) -> SplitBy<'a, false> {
+    assert!(!sep.is_empty(), "separator must be non-empty");
+    SplitBy { data, sep }
+}
+
+/// Returns a [`TokenSource`] that uses the fields of `data` separated by `sep` as tokens.
+/// Unlike [`split_by`] the separator is included at the end of each emitted token.
+/// This means that omitting the separator after the last field is detected by [`diff`](crate::diff).
+///
+/// # Panics
+///
+/// Panics if `sep` is empty.
+pub fn split_by_with_separator<'a>(data: &'a str, sep: &'a str) -> SplitBy<'a, true> {
+    assert!(!sep.is_empty(), "separator must be non-empty");
+    SplitBy { data, sep }
+}
+
 /// By default, a line diff is produced for a string
 impl<'a> TokenSource for &'a str {
     type Token = &'a str;
@@ -140,12 +220,242 @@ impl<'a, const INCLUDE_LINE_TERMINATOR: bool> TokenSource
         *self
     }
 
+    /// Extrapolates the number of lines from the number of bytes spanned by the first and the last 20 lines.
+    ///
+    /// The estimate is exact for inputs with at most 40 lines and never exceeds
+    /// the length of the input (every line spans at least one byte).
+    /// Inputs whose first and last lines are much shorter than the lines in between
+    /// are still overestimated.
     fn estimate_tokens(&self) -> u32 {
-        let len: usize = self.take(20).map(|line| line.len()).sum();
-        if len == 0 {
-            100
-        } else {
-            (self.0.len() * 20 / len) as u32
+        let mut rem = *self;
+        let head_lines = rem.by_ref().take(SAMPLE_TOKENS).count();
+        if rem.0.is_empty() {
+            return head_lines as u32;
         }
+        let head_bytes = self.0.len() - rem.0.len();
+        let (tail_lines, tail_bytes) = sample_tail(rem.0, b"\n");
+        extrapolate_tokens(
+            self.0.len(),
+            head_lines + tail_lines,
+            head_bytes + tail_bytes,
+        )
+    }
+}
+
+/// The number of tokens sampled at the start and at the end of an input to estimate its number of tokens.
+const SAMPLE_TOKENS: usize = 20;
+
+/// Counts up to [`SAMPLE_TOKENS`] tokens separated by `sep` at the end of `data`, which must not be empty.
+/// Returns the number of counted tokens and the number of bytes they span.
+///
+/// `sep` is searched backwards, so a separator that can overlap itself
+/// may be matched at a different position than a forward search would match it.
+fn sample_tail(data: &[u8], sep: &[u8]) -> (usize, usize) {
+    // a trailing separator does not start a new token
+    let mut rem = data.strip_suffix(sep).unwrap_or(data);
+    for tokens in 1..=SAMPLE_TOKENS {
+        match rem.windows(sep.len()).rposition(|window| window == sep) {
+            Some(pos) => rem = &rem[..pos],
+            None => return (tokens, data.len()),
+        }
+    }
+    (SAMPLE_TOKENS, data.len() - rem.len() - sep.len())
+}
+
+/// Estimates the number of tokens in an input of `len` bytes
+/// from a sample of `sampled_tokens` tokens that span `sampled_bytes` bytes.
+/// Every token is assumed to span at least one byte, so the estimate never exceeds `len`.
+fn extrapolate_tokens(len: usize, sampled_tokens: usize, sampled_bytes: usize) -> u32 {
+    let estimate = len * sampled_tokens / sampled_bytes;
+    estimate.clamp(sampled_tokens, len).min(u32::MAX as usize) as u32
+}
+
+/// Returns a [`TokenSource`] that yields the tokens of `source` but compares them by the
+/// key returned by `key` instead of the tokens themselves.
+///
+/// This allows customizing what is considered a change (for example ignoring whitespace or comments)
+/// without implementing a custom [`TokenSource`].
+/// Tokens with the same key are interned as the same [`Token`](crate::intern::Token),
+/// so the [`Interner`](crate::intern::Interner) only retains the token that was interned first.
+///
+/// ```
+/// use imara_diff::intern::InternedInput;
+/// use imara_diff::sink::Counter;
+/// use imara_diff::sources::{by_key, lines};
+/// use imara_diff::{diff, Algorithm};
+///
+/// let ignore_case = |line: &&str| line.to_lowercase();
+/// let input = InternedInput::new(
+///     by_key(lines("foo\nbar"), ignore_case),
+///     by_key(lines("FOO\nbar"), ignore_case),
+/// );
+/// let changes = diff(Algorithm::Histogram, &input, Counter::default());
+/// assert_eq!(changes.total(), 0);
+/// ```
+pub fn by_key<S, K, F>(source: S, key: F) -> ByKey<S, F>
+where
+    S: TokenSource,
+    K: Hash + Eq,
+    F: Fn(&S::Token) -> K + Clone,
+{
+    ByKey { inner: source, key }
+}
+
+/// A [`TokenSource`] that returns fixed size chunks of a byte slice as tokens.
+/// See [`byte_chunks`] for details
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ByteChunks<'a> {
+    data: &'a [u8],
+    size: usize,
+}
+
+impl<'a> TokenSource for ByteChunks<'a> {
+    type Token = &'a [u8];
+
+    type Tokenizer = Chunks<'a, u8>;
+
+    fn tokenize(&self) -> Self::Tokenizer {
+        self.data.chunks(self.size)
+    }
+
+    fn estimate_tokens(&self) -> u32 {
+        let chunks = self.data.len() / self.size + (self.data.len() % self.size != 0) as usize;
+        chunks as u32
+    }
+}
+
+/// A [`TokenSource`] that returns the fields of a `str` separated by a custom separator as tokens.
+/// See [`split_by`] and [`split_by_with_separator`] for details
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SplitBy<'a, const INCLUDE_SEPARATOR: bool> {
+    data: &'a str,
+    sep: &'a str,
+}
+
+impl<'a, const INCLUDE_SEPARATOR: bool> Iterator for SplitBy<'a, INCLUDE_SEPARATOR> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let Some(field_len) = self.data.find(self.sep) else {
+            return Some(take(&mut self.data));
+        };
+        let (mut field, rem) = self.data.split_at(field_len + self.sep.len());
+        self.data = rem;
+        if !INCLUDE_SEPARATOR {
+            field = &field[..field_len];
+        }
+        Some(field)
+    }
+}
+
+impl<'a, const INCLUDE_SEPARATOR: bool> TokenSource for SplitBy<'a, INCLUDE_SEPARATOR> {
+    type Token = &'a str;
+
+    type Tokenizer = Self;
+
+    fn tokenize(&self) -> Self::Tokenizer {
+        *self
+    }
+
+    /// Extrapolates the number of fields like [`ByteLines`] extrapolates the number of lines.
+    ///
+    /// The estimate is exact for inputs with at most 40 fields.
+    /// For larger inputs the last fields are sampled by searching for `sep` backwards,
+    /// which only approximates the fields if `sep` can overlap itself (like `::` in `a:::b`).
+    fn estimate_tokens(&self) -> u32 {
+        let mut rem = *self;
+        let head_fields = rem.by_ref().take(SAMPLE_TOKENS).count();
+        // the fields after the head are counted with the tokenizer if there are only a few of them,
+        // searching backwards can split differently than the tokenizer
+        let mut tail = rem;
+        let tail_fields = tail.by_ref().take(SAMPLE_TOKENS).count();
+        if tail.data.is_empty() {
+            return (head_fields + tail_fields) as u32;
+        }
+        let head_bytes = self.data.len() - rem.data.len();
+        let (tail_fields, tail_bytes) = sample_tail(rem.data.as_bytes(), self.sep.as_bytes());
+        extrapolate_tokens(
+            self.data.len(),
+            head_fields + tail_fields,
+            head_bytes + tail_bytes,
+        )
+    }
+}
+
+/// A [`TokenSource`] that compares the tokens of a different source by a key.
+/// See [`by_key`] for details.
+#[derive(Clone, Copy)]
+pub struct ByKey<S, F> {
+    inner: S,
+    key: F,
+}
+
+impl<I, K, F> Iterator for ByKey<I, F>
+where
+    I: Iterator,
+    F: Fn(&I::Item) -> K,
+{
+    type Item = Keyed<I::Item, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.inner.next()?;
+        let key = (self.key)(&token);
+        Some(Keyed { token, key })
+    }
+}
+
+impl<S, K, F> TokenSource for ByKey<S, F>
+where
+    S: TokenSource,
+    K: Hash + Eq,
+    F: Fn(&S::Token) -> K + Clone,
+{
+    type Token = Keyed<S::Token, K>;
+
+    type Tokenizer = ByKey<S::Tokenizer, F>;
+
+    fn tokenize(&self) -> Self::Tokenizer {
+        ByKey {
+            inner: self.inner.tokenize(),
+            key: self.key.clone(),
+        }
+    }
+
+    fn estimate_tokens(&self) -> u32 {
+        self.inner.estimate_tokens()
+    }
+}
+
+/// A token produced by [`by_key`].
+/// Equality and hashing only consider the `key`, while the
+/// original `token` is used for display.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyed<T, K> {
+    /// The token yielded by the wrapped source.
+    pub token: T,
+    /// The key used to compare the token.
+    pub key: K,
+}
+
+impl<T, K: PartialEq> PartialEq for Keyed<T, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T, K: Eq> Eq for Keyed<T, K> {}
+
+impl<T, K: Hash> Hash for Keyed<T, K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+impl<T: Display, K> Display for Keyed<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.token.fmt(f)
     }
 }
@@ -1,12 +1,20 @@
 use std::fs::read_to_string;
-use std::mem::swap;
+use std::mem::take;
+use std::ops::Range;
 use std::path::PathBuf;
 
 use expect_test::{expect, expect_file};
 
-use crate::intern::InternedInput;
-use crate::sink::Counter;
-use crate::{diff, Algorithm, UnifiedDiffBuilder};
+use crate::intern::{InternedInput, Interner, Token, TokenSource};
+use crate::sink::{Counter, Sink, Span, SpanSink};
+use crate::sources::{
+    by_key, byte_chunks, byte_lines, byte_lines_with_terminator, lines, lines_with_terminator,
+    split_by, split_by_with_separator,
+};
+use crate::{
+    are_equal, diff, diff_with_tokens, try_diff_with_tokens, util, Algorithm, CombinedHunk,
+    SideHunk, TokenOutOfRange, UnifiedDiffBuilder, WindowedDiff,
+};
 
 #[test]
 fn replace() {
@@ -87,7 +95,7 @@ fn simple_insert() {
           "#]]
         .assert_eq(&res);
 
-        swap(&mut input.before, &mut input.after);
+        input.swap_sides();
 
         let res = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
         expect![[r#"
@@ -100,7 +108,7 @@ fn simple_insert() {
             "#]]
         .assert_eq(&res);
 
-        swap(&mut input.before, &mut input.after);
+        input.swap_sides();
     }
 }
 
@@ -154,3 +162,1016 @@ fn complex_diffs() {
         }
     }
 }
+
+#[test]
+fn token_ids_follow_first_appearance() {
+    let before = "a\nb\na\nc\n";
+    let after = "c\nd\nb\ne\n";
+    let input = InternedInput::new(before, after);
+    let ids = |tokens: &[Token]| tokens.iter().map(|token| token.0).collect::<Vec<_>>();
+    assert_eq!(ids(&input.before), [0, 1, 0, 2]);
+    assert_eq!(ids(&input.after), [2, 3, 1, 4]);
+    for (id, line) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+        assert_eq!(input.interner[Token(id as u32)], line);
+    }
+
+    let again = InternedInput::new(before, after);
+    assert_eq!(input.before, again.before);
+    assert_eq!(input.after, again.after);
+}
+
+/// All algorithms, including the (slow) `MyersMinimal` that is not part of `Algorithm::ALL`.
+const ALL_ALGORITHMS: [Algorithm; 3] = [
+    Algorithm::Histogram,
+    Algorithm::Myers,
+    Algorithm::MyersMinimal,
+];
+
+/// A xorshift pseudo random number generator, a fixed seed keeps tests reproducible.
+fn xorshift(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+fn changes<T>(algorithm: Algorithm, input: &InternedInput<T>) -> Vec<(Range<u32>, Range<u32>)> {
+    let mut changes = Vec::new();
+    diff(algorithm, input, |before, after| {
+        changes.push((before, after))
+    });
+    changes
+}
+
+#[test]
+fn windowed_diff() {
+    let before = "a\nb\nc\nd\ne\nf\n";
+    let appended = ["x\n", "a\nb\n", "c\ny\ne\nf\n", "z\n"];
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let mut windowed = WindowedDiff::new(before);
+        let mut after = String::new();
+        for chunk in appended {
+            after.push_str(chunk);
+            windowed.append(lines(chunk));
+            let input = InternedInput::new(before, &*after);
+            let mut windowed_changes = Vec::new();
+            windowed.diff(algorithm, |before, after| {
+                windowed_changes.push((before, after))
+            });
+            assert_eq!(windowed_changes, changes(algorithm, &input));
+        }
+        windowed.reset();
+        assert!(windowed.input().after.is_empty());
+        assert_eq!(windowed.input().interner.num_tokens(), 6);
+    }
+}
+
+/// Asserts that `MyersMinimal` does not produce a longer edit-script than any other algorithm.
+fn assert_minimal_distance(before: &[Token], after: &[Token], num_tokens: u32) {
+    let distance = |algorithm| {
+        diff_with_tokens(algorithm, before, after, num_tokens, Counter::default()).total()
+    };
+    let minimal_distance = distance(Algorithm::MyersMinimal);
+    for algorithm in Algorithm::ALL {
+        let algorithm_distance = distance(algorithm);
+        assert!(
+            minimal_distance <= algorithm_distance,
+            "MyersMinimal produced {minimal_distance} changes but {algorithm:?} only {algorithm_distance}\nbefore: {before:?}\nafter: {after:?}"
+        );
+    }
+}
+
+#[test]
+#[cfg(not(miri))]
+fn minimal_distance_corpus() {
+    let test_dir = project_root().join("tests");
+    let before = read_to_string(test_dir.join("helix_syntax.rs.before")).unwrap();
+    let after = read_to_string(test_dir.join("helix_syntax.rs.after")).unwrap();
+    let input = InternedInput::new(&*before, &*after);
+    assert_minimal_distance(&input.before, &input.after, input.interner.num_tokens());
+}
+
+#[test]
+#[cfg(not(miri))]
+fn minimal_distance_random() {
+    let mut next = xorshift(0x2545_f491_4f6c_dd1du64);
+    for _ in 0..1000 {
+        let num_tokens = 2 + (next() % 20) as u32;
+        let len = next() % 200;
+        let before: Vec<_> = (0..len)
+            .map(|_| Token((next() % num_tokens as u64) as u32))
+            .collect();
+        let mut after = before.clone();
+        for _ in 0..next() % 40 {
+            let pos = next() as usize % (after.len() + 1);
+            if pos < after.len() && next() % 2 == 0 {
+                after.remove(pos);
+            } else {
+                after.insert(pos, Token((next() % num_tokens as u64) as u32));
+            }
+        }
+        assert_minimal_distance(&before, &after, num_tokens);
+    }
+}
+
+#[test]
+fn udiff_header_after_common_prefix_and_postfix() {
+    let before: String = (0..200).map(|i| format!("line {i}\n")).collect();
+    let after: String = (0..200)
+        .map(|i| match i {
+            10 => "changed 10\n".to_owned(),
+            100 => "line 100\ninserted\n".to_owned(),
+            150 => String::new(),
+            _ => format!("line {i}\n"),
+        })
+        .collect();
+    let input = InternedInput::new(&*before, &*after);
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let diff = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
+        expect![[r#"
+            @@ -8,7 +8,7 @@
+             line 7
+             line 8
+             line 9
+            -line 10
+            +changed 10
+             line 11
+             line 12
+             line 13
+            @@ -99,6 +99,7 @@
+             line 98
+             line 99
+             line 100
+            +inserted
+             line 101
+             line 102
+             line 103
+            @@ -148,7 +149,6 @@
+             line 147
+             line 148
+             line 149
+            -line 150
+             line 151
+             line 152
+             line 153
+        "#]]
+        .assert_eq(&diff);
+    }
+}
+
+#[test]
+fn combined_diff() {
+    let base = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let ours = "a\nb\nx\nd\ne\nf\ng\nh\n";
+    let theirs = "a\nb\ny\nz\nd\ne\nf\nh\ni\n";
+    let mut input = InternedInput::new(base, ours);
+    let ours = take(&mut input.after);
+    input.update_after(lines(theirs));
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let hunks = crate::combined_diff(
+            algorithm,
+            &input.before,
+            &[&ours, &input.after],
+            input.interner.num_tokens(),
+        );
+        let side = |range, changed| SideHunk { range, changed };
+        assert_eq!(
+            hunks,
+            [
+                CombinedHunk {
+                    base: 2..3,
+                    sides: vec![side(2..3, true), side(2..4, true)],
+                },
+                CombinedHunk {
+                    base: 6..7,
+                    sides: vec![side(6..7, false), side(7..7, true)],
+                },
+                CombinedHunk {
+                    base: 8..8,
+                    sides: vec![side(8..8, false), side(8..9, true)],
+                },
+            ]
+        );
+    }
+}
+
+#[test]
+fn by_key_ignores_comments() {
+    let before = r#"fn foo() {
+    let x = 1; // one
+    let y = 2;
+    bar(x, y);
+}"#;
+    let after = r#"fn foo() {
+    let x = 1; // the answer
+    let y = 3; // changed
+    bar(x, y);
+}"#;
+    let strip_comment = |line: &&str| match line.find("//") {
+        Some(comment) => line[..comment].trim_end().to_owned(),
+        None => line.to_string(),
+    };
+    let input = InternedInput::new(
+        by_key(lines(before), strip_comment),
+        by_key(lines(after), strip_comment),
+    );
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let diff = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
+        expect![[r#"
+            @@ -1,5 +1,5 @@
+             fn foo() {
+                 let x = 1; // one
+            -    let y = 2;
+            +    let y = 3; // changed
+                 bar(x, y);
+             }
+        "#]]
+        .assert_eq(&diff);
+    }
+}
+
+#[test]
+fn fill_matches_new() {
+    let mut input = InternedInput::new("a\nb\nc\n", "x\ny\n");
+    let before = "fn foo() {\n    bar();\n}\n";
+    let after = "fn foo() {\n    baz();\n}\n";
+    input.fill(before, after);
+    let fresh = InternedInput::new(before, after);
+    assert_eq!(input.before, fresh.before);
+    assert_eq!(input.after, fresh.after);
+    assert_eq!(input.interner.num_tokens(), fresh.interner.num_tokens());
+    for algorithm in Algorithm::ALL {
+        assert_eq!(
+            diff(algorithm, &input, UnifiedDiffBuilder::new(&input)),
+            diff(algorithm, &fresh, UnifiedDiffBuilder::new(&fresh))
+        );
+    }
+}
+
+#[test]
+fn remap_interner() {
+    let first = InternedInput::new("a\nb\nc\n", "c\nd\n");
+    let mut second = InternedInput::new("d\ne\n", "a\nf\n");
+    let remap = first.interner.remap_into(&mut second.interner);
+    assert_eq!(remap, [Token(2), Token(4), Token(5), Token(0)]);
+    for (id, &remapped) in remap.iter().enumerate() {
+        assert_eq!(first.interner[Token(id as u32)], second.interner[remapped]);
+    }
+    assert_eq!(second.interner.num_tokens(), 6);
+}
+
+#[test]
+fn spans_tile_input() {
+    let before = "a\nb\nc\nd\ne\n";
+    let after = "x\na\nc\ny\nd\nz\n";
+    let input = InternedInput::new(before, after);
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let spans = diff(algorithm, &input, SpanSink::new());
+        assert_eq!(
+            spans,
+            [
+                Span::Insert(0..1),
+                Span::Keep {
+                    before: 0..1,
+                    after: 1..2
+                },
+                Span::Delete(1..2),
+                Span::Keep {
+                    before: 2..3,
+                    after: 2..3
+                },
+                Span::Insert(3..4),
+                Span::Keep {
+                    before: 3..4,
+                    after: 4..5
+                },
+                Span::Delete(4..5),
+                Span::Insert(5..6),
+            ]
+        );
+
+        let (mut before_pos, mut after_pos) = (0, 0);
+        for span in spans {
+            match span {
+                Span::Keep { before, after } => {
+                    assert_eq!(before.start, before_pos);
+                    assert_eq!(after.start, after_pos);
+                    assert_eq!(before.len(), after.len());
+                    before_pos = before.end;
+                    after_pos = after.end;
+                }
+                Span::Insert(after) => {
+                    assert_eq!(after.start, after_pos);
+                    after_pos = after.end;
+                }
+                Span::Delete(before) => {
+                    assert_eq!(before.start, before_pos);
+                    before_pos = before.end;
+                }
+            }
+        }
+        assert_eq!(before_pos, input.before.len() as u32);
+        assert_eq!(after_pos, input.after.len() as u32);
+    }
+}
+
+#[test]
+fn byte_chunk_diff() {
+    let before: Vec<u8> = (0..64).collect();
+    let mut after = before.clone();
+    after[41] = 0xff;
+    after.extend_from_slice(&[1, 2, 3]);
+    let input = InternedInput::new(byte_chunks(&before, 8), byte_chunks(&after, 8));
+    assert_eq!(input.before.len(), 8);
+    assert_eq!(input.after.len(), 9);
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let mut changes = Vec::new();
+        diff(algorithm, &input, |before, after| {
+            changes.push((before, after))
+        });
+        assert_eq!(changes, [(5..6, 5..6), (8..8, 8..9)]);
+    }
+}
+
+#[test]
+fn byte_chunks_larger_than_data() {
+    let data: Vec<u8> = (0..10).collect();
+    for size in [10, 11, 1 << 20, usize::MAX] {
+        let chunks = byte_chunks(&data, size);
+        assert_eq!(chunks.estimate_tokens(), 1);
+        let input = InternedInput::new(chunks, chunks);
+        assert_eq!(input.before.len(), 1);
+    }
+    assert_eq!(byte_chunks(&[], usize::MAX).estimate_tokens(), 0);
+}
+
+#[test]
+fn swap_sides_inverts_diff() {
+    let before = "a\nb\nc\nd\n";
+    let after = "a\nx\nc\nd\ne\n";
+    let mut input = InternedInput::new(before, after);
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let forward = changes(algorithm, &input);
+        input.swap_sides();
+        let backward = changes(algorithm, &input);
+        input.swap_sides();
+        let inverted: Vec<_> = forward
+            .into_iter()
+            .map(|(before, after)| (after, before))
+            .collect();
+        assert_eq!(backward, inverted);
+    }
+}
+
+#[test]
+fn empty_inputs() {
+    let tokens = [Token(0), Token(1), Token(0)];
+    for algorithm in ALL_ALGORITHMS {
+        println!("{algorithm:?}");
+        let changes = |before: &[Token], after: &[Token], num_tokens| {
+            let mut changes = Vec::new();
+            diff_with_tokens(algorithm, before, after, num_tokens, |before, after| {
+                changes.push((before, after))
+            });
+            changes
+        };
+        assert_eq!(changes(&[], &[], 0), []);
+        assert_eq!(changes(&[], &tokens, 2), [(0..0, 0..3)]);
+        assert_eq!(changes(&tokens, &[], 2), [(0..3, 0..0)]);
+
+        let input = InternedInput::new("", "");
+        assert_eq!(input.interner.num_tokens(), 0);
+        assert_eq!(diff(algorithm, &input, UnifiedDiffBuilder::new(&input)), "");
+    }
+}
+
+#[test]
+fn interner_eviction() {
+    let mut interner = Interner::with_max_tokens(3);
+    assert_eq!(interner.intern("a"), Token(0));
+    assert_eq!(interner.intern("b"), Token(1));
+    assert_eq!(interner.intern("c"), Token(2));
+    // "a" is now the most recently used token
+    assert_eq!(interner.intern("a"), Token(0));
+    // evicts "b"
+    assert_eq!(interner.intern("d"), Token(1));
+    assert_eq!(interner.num_tokens(), 3);
+    assert_eq!(interner[Token(1)], "d");
+    // evicts "c"
+    assert_eq!(interner.intern("b"), Token(2));
+    assert_eq!(interner.intern("a"), Token(0));
+    assert_eq!(interner.intern("d"), Token(1));
+    // evicts "b"
+    assert_eq!(interner.intern("e"), Token(2));
+
+    interner.clear();
+    assert_eq!(interner.intern("a"), Token(0));
+    assert_eq!(interner.intern("b"), Token(1));
+    assert_eq!(interner.intern("c"), Token(2));
+    // evicts "a"
+    assert_eq!(interner.intern("d"), Token(0));
+}
+
+#[test]
+#[should_panic(expected = "erase_tokens_after is not supported for interners with a token limit")]
+fn interner_eviction_erase() {
+    let mut interner = Interner::with_max_tokens(2);
+    interner.intern("a");
+    interner.intern("b");
+    // evicts "a", so the token interned last has the smallest id
+    assert_eq!(interner.intern("c"), Token(0));
+    interner.erase_tokens_after(Token(1));
+}
+
+#[test]
+fn interner_eviction_stream() {
+    let files: Vec<String> = (0..20)
+        .map(|i| (i..i + 10).map(|line| format!("{line}\n")).collect())
+        .collect();
+    let mut input = InternedInput {
+        interner: Interner::with_max_tokens(24),
+        ..InternedInput::default()
+    };
+    for pair in files.windows(2) {
+        input.update_before(lines(&pair[0]));
+        input.update_after(lines(&pair[1]));
+        let expected = InternedInput::new(&*pair[0], &*pair[1]);
+        for algorithm in Algorithm::ALL {
+            assert_eq!(
+                diff(algorithm, &input, UnifiedDiffBuilder::new(&input)),
+                diff(algorithm, &expected, UnifiedDiffBuilder::new(&expected))
+            );
+        }
+        assert!(input.interner.num_tokens() <= 24);
+    }
+}
+
+#[test]
+fn token_out_of_range() {
+    let input = InternedInput::new("a\nb\nc\n", "a\nd\nc\n");
+    let num_tokens = input.interner.num_tokens();
+    for algorithm in ALL_ALGORITHMS {
+        let res = try_diff_with_tokens(
+            algorithm,
+            &input.before,
+            &input.after,
+            num_tokens - 1,
+            Counter::default(),
+        );
+        let err = res.err().unwrap();
+        assert_eq!(
+            err,
+            TokenOutOfRange {
+                token: Token(3),
+                num_tokens: 3
+            }
+        );
+        assert_eq!(err.to_string(), "token 3 is out of range for 3 tokens");
+
+        let res = try_diff_with_tokens(
+            algorithm,
+            &input.before,
+            &input.after,
+            num_tokens,
+            Counter::default(),
+        );
+        assert_eq!(res.unwrap().total(), 2);
+    }
+}
+
+#[test]
+fn new_with_key_normalizes_whitespace() {
+    let before = "fn foo() {\n    bar(1,2);\n    baz();\n}\n";
+    let after = "fn  foo()  {\n  bar(1, 2);\n    baz(3);\n}\n";
+    let input = InternedInput::new_with_key(before, after, |line: &&str| {
+        line.split_whitespace().collect::<String>()
+    });
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let diff = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
+        expect![[r#"
+            @@ -1,4 +1,4 @@
+             fn foo() {
+                 bar(1,2);
+            -    baz();
+            +    baz(3);
+             }
+        "#]]
+        .assert_eq(&diff);
+    }
+    assert_eq!(input.interner[input.after[0]].token, "fn foo() {");
+}
+
+#[test]
+fn line_terminators() {
+    let data = "a\nb\r\nc";
+    assert_eq!(lines(data).collect::<Vec<_>>(), ["a", "b", "c"]);
+    assert_eq!(
+        lines_with_terminator(data).collect::<Vec<_>>(),
+        ["a\n", "b\r\n", "c"]
+    );
+    let data = data.as_bytes();
+    assert_eq!(
+        byte_lines(data).collect::<Vec<_>>(),
+        [&b"a"[..], b"b", b"c"]
+    );
+    assert_eq!(
+        byte_lines_with_terminator(data).collect::<Vec<_>>(),
+        [&b"a\n"[..], b"b\r\n", b"c"]
+    );
+
+    // only the sources that include the terminator detect changes to it
+    let (before, after) = ("a\nb\n", "a\r\nb");
+    let input = InternedInput::new(lines(before), lines(after));
+    assert_eq!(
+        diff(Algorithm::Histogram, &input, Counter::default()).total(),
+        0
+    );
+    let input = InternedInput::new(lines_with_terminator(before), lines_with_terminator(after));
+    assert_eq!(
+        diff(Algorithm::Histogram, &input, Counter::default()).total(),
+        4
+    );
+}
+
+#[test]
+fn histogram_tie_break() {
+    // "x\ny\n" occurs twice in `before`, both anchors are equally good
+    let before = "1\nx\ny\n2\nx\ny\n3\n";
+    let after = "4\nx\ny\n5\n";
+    let input = InternedInput::new(before, after);
+    assert_eq!(
+        changes(Algorithm::Histogram, &input),
+        [(0..1, 0..1), (3..7, 3..4)]
+    );
+}
+
+#[test]
+fn largest_common_block() {
+    let before = "1\na\nb\n2\nc\nd\ne\nf\n3\n";
+    let after = "c\nd\n4\na\nb\nc\nd\ne\nf\n5\n";
+    let input = InternedInput::new(before, after);
+    let num_tokens = input.interner.num_tokens();
+    assert_eq!(
+        util::largest_common_block(&input.before, &input.after, num_tokens),
+        Some((4..8, 5..9))
+    );
+    assert_eq!(
+        util::largest_common_block(&input.after, &input.before, num_tokens),
+        Some((5..9, 4..8))
+    );
+    // out of range tokens are handled like in `diff_with_tokens`
+    assert_eq!(
+        util::largest_common_block(&input.before, &input.after, 0),
+        Some((4..8, 5..9))
+    );
+
+    let input = InternedInput::new("a\nb\n", "c\nd\n");
+    let num_tokens = input.interner.num_tokens();
+    assert_eq!(
+        util::largest_common_block(&input.before, &input.after, num_tokens),
+        None
+    );
+    assert_eq!(
+        util::largest_common_block(&[], &input.after, num_tokens),
+        None
+    );
+
+    // compare against a brute force search on random input
+    let mut next = xorshift(0x1234_5678_9abc_def1u64);
+    for _ in 0..200 {
+        let num_tokens = 1 + (next() % 8) as u32;
+        let mut file = || -> Vec<_> {
+            let len = next() % 40;
+            (0..len)
+                .map(|_| Token((next() % num_tokens as u64) as u32))
+                .collect()
+        };
+        let before = file();
+        let after = file();
+        let mut expected = None;
+        for after_pos in 0..after.len() {
+            for before_pos in 0..before.len() {
+                let len = util::common_prefix(&before[before_pos..], &after[after_pos..]);
+                if len != 0 && expected.map_or(true, |(_, _, best_len)| len > best_len) {
+                    expected = Some((before_pos as u32, after_pos as u32, len));
+                }
+            }
+        }
+        let expected =
+            expected.map(|(before, after, len)| (before..before + len, after..after + len));
+        assert_eq!(
+            util::largest_common_block(&before, &after, num_tokens),
+            expected
+        );
+    }
+}
+
+#[test]
+fn offsets_after_common_prefix() {
+    let prefix: String = (0..200).map(|i| format!("p{i}\n")).collect();
+    let middle: String = (0..50).map(|i| format!("m{i}\n")).collect();
+    let suffix: String = (0..10).map(|i| format!("s{i}\n")).collect();
+
+    let before = format!("{prefix}x\n{middle}y\n{suffix}");
+    let after = format!("{prefix}X1\nX2\n{middle}{suffix}z\n");
+    let input = InternedInput::new(&*before, &*after);
+    let expected = vec![
+        (200..201, 200..202),
+        (251..252, 252..252),
+        (262..262, 262..263),
+    ];
+    for algorithm in ALL_ALGORITHMS {
+        println!("{algorithm:?}");
+        assert_eq!(changes(algorithm, &input), expected);
+    }
+
+    // tokens that only occur in one file are pruned by the myers preprocessing
+    let before = format!("{prefix}a\nb\n{middle}c\n{suffix}");
+    let after = format!("{prefix}{middle}d\n{suffix}");
+    let input = InternedInput::new(&*before, &*after);
+    let expected = vec![(200..202, 200..200), (252..253, 250..251)];
+    for algorithm in ALL_ALGORITHMS {
+        println!("{algorithm:?}");
+        assert_eq!(changes(algorithm, &input), expected);
+    }
+}
+
+#[derive(Default)]
+struct RecordUnchanged {
+    sections: Vec<(bool, Range<u32>, Range<u32>)>,
+}
+
+impl Sink for RecordUnchanged {
+    type Out = Vec<(bool, Range<u32>, Range<u32>)>;
+
+    fn process_change(&mut self, before: Range<u32>, after: Range<u32>) {
+        self.sections.push((true, before, after))
+    }
+
+    fn process_unchanged(&mut self, before: Range<u32>, after: Range<u32>) {
+        self.sections.push((false, before, after))
+    }
+
+    fn finish(self) -> Self::Out {
+        self.sections
+    }
+}
+
+#[test]
+fn unchanged_sections_tile_input() {
+    let inputs = [
+        ("", ""),
+        ("a\nb\n", "a\nb\n"),
+        ("a\nb\n", ""),
+        ("a\nb\nc\nd\ne\n", "x\nb\ny\nd\n"),
+        ("a\nb\nc\nd\ne\n", "a\nx\nc\nd\ne\nf\n"),
+        ("x\ny\na\nb\nz\n", "a\nb\nw\na\nb\n"),
+    ];
+    for (before, after) in inputs {
+        let input = InternedInput::new(before, after);
+        for algorithm in ALL_ALGORITHMS {
+            println!("{algorithm:?} {before:?} {after:?}");
+            let sections = diff(algorithm, &input, RecordUnchanged::default());
+            let mut before_pos = 0;
+            let mut after_pos = 0;
+            let mut last_unchanged = false;
+            for (changed, before, after) in &sections {
+                assert_eq!(before.start, before_pos);
+                assert_eq!(after.start, after_pos);
+                if !changed {
+                    assert!(!last_unchanged, "adjacent unchanged sections");
+                    assert!(!before.is_empty());
+                    assert_eq!(before.len(), after.len());
+                    assert_eq!(
+                        input.before[before.start as usize..before.end as usize],
+                        input.after[after.start as usize..after.end as usize]
+                    );
+                }
+                last_unchanged = !changed;
+                before_pos = before.end;
+                after_pos = after.end;
+            }
+            assert_eq!(before_pos, input.before.len() as u32);
+            assert_eq!(after_pos, input.after.len() as u32);
+            let changes: Vec<_> = sections
+                .into_iter()
+                .filter(|(changed, _, _)| *changed)
+                .map(|(_, before, after)| (before, after))
+                .collect();
+            assert_eq!(changes, self::changes(algorithm, &input));
+        }
+    }
+}
+
+#[test]
+fn sqrt_approximation() {
+    let expected = [
+        (0, 1),
+        (1, 1),
+        (2, 2),
+        (3, 2),
+        (4, 2),
+        (15, 4),
+        (16, 4),
+        (17, 4),
+        (63, 8),
+        (64, 8),
+        (100, 8),
+        (255, 16),
+        (256, 16),
+        (u32::MAX as usize, 1 << 16),
+    ];
+    for (val, sqrt) in expected {
+        assert_eq!(util::sqrt(val), sqrt, "sqrt({val})");
+    }
+    assert_ne!(util::sqrt(usize::MAX), 0);
+}
+
+#[test]
+fn estimate_line_count() {
+    fn assert_estimate(data: &str, max_factor: usize) {
+        let lines = lines(data).count();
+        for estimate in [
+            byte_lines(data.as_bytes()).estimate_tokens(),
+            byte_lines_with_terminator(data.as_bytes()).estimate_tokens(),
+        ] {
+            let estimate = estimate as usize;
+            println!("{lines} lines, estimated {estimate}");
+            assert!(estimate <= data.len().max(1));
+            assert!(estimate <= lines * max_factor && lines <= estimate * max_factor);
+        }
+    }
+
+    // short inputs are counted exactly
+    assert_estimate("", 1);
+    assert_estimate("foo", 1);
+    assert_estimate("a\nb\nc", 1);
+    assert_estimate(&"x".repeat(100_000), 1);
+    assert_estimate(&"\n".repeat(20), 1);
+    // empty lines still span their line terminator
+    assert_estimate(&"\n".repeat(1000), 1);
+    assert_estimate(&"\r\n".repeat(1000), 1);
+
+    let alternating: String = (0..1000)
+        .map(|i| {
+            if i % 2 == 0 {
+                "a\n".to_owned()
+            } else {
+                "b".repeat(200) + "\n"
+            }
+        })
+        .collect();
+    assert_estimate(&alternating, 2);
+
+    // the estimate is extrapolated from the first and the last lines
+    let tiny_then_huge = "a\n".repeat(20) + &("b".repeat(10_000) + "\n").repeat(10);
+    assert_estimate(&tiny_then_huge, 1);
+    let tiny_then_huge = "a\n".repeat(100) + &("b".repeat(10_000) + "\n").repeat(100);
+    assert_estimate(&tiny_then_huge, 2);
+    let huge_then_tiny = ("b".repeat(10_000) + "\n").repeat(100) + &"a\n".repeat(100);
+    assert_estimate(&huge_then_tiny, 2);
+    let ragged_end = "a\n".repeat(100) + "b\n\nc\r\nd";
+    assert_estimate(&ragged_end, 1);
+}
+
+#[test]
+fn equal_sides() {
+    let equal = ("a\nb\nc\n", "a\nb\nc\n");
+    let first_line_differs = ("a\nb\nc\n", "x\nb\nc\n");
+    let last_line_differs = ("a\nb\nc\n", "a\nb\nc\nd\n");
+    for ((before, after), expected) in [
+        (equal, true),
+        (first_line_differs, false),
+        (last_line_differs, false),
+        (("", ""), true),
+    ] {
+        assert_eq!(are_equal(before, after), expected);
+        assert_eq!(are_equal(before.as_bytes(), after.as_bytes()), expected);
+        let input = InternedInput::new(before, after);
+        assert_eq!(input.sides_equal(), expected);
+        assert_eq!(changes(Algorithm::Histogram, &input).is_empty(), expected);
+    }
+    // the line terminator is ignored by the default tokenizer
+    assert!(are_equal("a\nb", "a\nb\n"));
+    assert!(!are_equal(
+        lines_with_terminator("a\nb"),
+        lines_with_terminator("a\nb\n")
+    ));
+}
+
+#[test]
+fn token_id() {
+    for id in [0, 1, 42, u32::MAX] {
+        let token = Token::new(id);
+        assert_eq!(token.id(), id);
+        assert_eq!(token, Token(id));
+        assert_eq!(token, Token::from(id));
+        assert_eq!(u32::from(token), id);
+    }
+    assert_eq!(format!("{:?}", Token::new(5)), "Token(5)");
+
+    let input = InternedInput::new("a\nb\na\n", "b\n");
+    let ids: Vec<_> = input.before.iter().map(|token| token.id()).collect();
+    assert_eq!(ids, [0, 1, 0]);
+    assert!(input
+        .after
+        .iter()
+        .all(|token| token.id() < input.interner.num_tokens()));
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn nfc_lines() {
+    use crate::sources::lines_nfc;
+
+    // precomposed vs decomposed accents
+    let before = "caf\u{e9}\nna\u{ef}ve\nfoo\n";
+    let after = "cafe\u{301}\nnai\u{308}ve\nbar\n";
+    assert_eq!(
+        changes(Algorithm::Histogram, &InternedInput::new(before, after)),
+        vec![(0..3, 0..3)]
+    );
+
+    let input = InternedInput::new(lines_nfc(before), lines_nfc(after));
+    for algorithm in Algorithm::ALL {
+        assert_eq!(changes(algorithm, &input), vec![(2..3, 2..3)]);
+    }
+    // the original text is preserved
+    assert_eq!(input.interner[input.before[0]].token, "caf\u{e9}");
+    assert_eq!(input.interner[input.after[2]].token, "bar");
+}
+
+#[test]
+fn tokens_out_of_range() {
+    let mut next = xorshift(0x9e37_79b9_7f4a_7c15u64);
+    for i in 0..200 {
+        let len = next() % 100;
+        let before: Vec<_> = (0..len).map(|_| Token((next() % 64) as u32)).collect();
+        let len = next() % 100;
+        let after: Vec<_> = (0..len).map(|_| Token((next() % 64) as u32)).collect();
+        // ids far outside of the valid range are handled as well
+        let shift = |file: &[Token]| -> Vec<_> {
+            file.iter().map(|token| Token(u32::MAX - token.0)).collect()
+        };
+        let num_tokens = (i % 8) as u32;
+        for algorithm in ALL_ALGORITHMS {
+            let mut expected = Vec::new();
+            diff_with_tokens(algorithm, &before, &after, 64, |before, after| {
+                expected.push((before, after))
+            });
+
+            let mut changes = Vec::new();
+            diff_with_tokens(algorithm, &before, &after, num_tokens, |before, after| {
+                changes.push((before, after))
+            });
+            assert_eq!(changes, expected, "{algorithm:?}");
+
+            let mut changes = Vec::new();
+            diff_with_tokens(
+                algorithm,
+                &shift(&before),
+                &shift(&after),
+                num_tokens,
+                |before, after| changes.push((before, after)),
+            );
+            assert_eq!(changes, expected, "{algorithm:?}");
+        }
+    }
+}
+
+#[test]
+fn split_by_separator() {
+    let fields: Vec<_> = split_by("a,,b,", ",").collect();
+    assert_eq!(fields, ["a", "", "b"]);
+    let fields: Vec<_> = split_by_with_separator("a,,b,", ",").collect();
+    assert_eq!(fields, ["a,", ",", "b,"]);
+    let fields: Vec<_> = split_by(",a", ",").collect();
+    assert_eq!(fields, ["", "a"]);
+    let fields: Vec<_> = split_by("a::b::c", "::").collect();
+    assert_eq!(fields, ["a", "b", "c"]);
+    assert_eq!(split_by("", ",").count(), 0);
+
+    let before = "id,name,,email,age";
+    let after = "id,name,phone,email,age,";
+    let input = InternedInput::new(split_by(before, ","), split_by(after, ","));
+    for algorithm in Algorithm::ALL {
+        assert_eq!(changes(algorithm, &input), vec![(2..3, 2..3)]);
+    }
+    // the missing trailing separator is only detected if it is part of the tokens
+    let input = InternedInput::new(
+        split_by_with_separator(before, ","),
+        split_by_with_separator(after, ","),
+    );
+    for algorithm in Algorithm::ALL {
+        assert_eq!(changes(algorithm, &input), vec![(2..3, 2..3), (4..5, 4..5)]);
+    }
+    assert_eq!(split_by(before, ",").estimate_tokens(), 5);
+    assert_eq!(split_by(&"abc::".repeat(100), "::").estimate_tokens(), 100);
+    // `:::` is split as `::` followed by `:`, just like the tokenizer splits it
+    let overlapping = "x::".repeat(20) + "a:::";
+    assert_eq!(split_by(&overlapping, "::").count(), 22);
+    assert_eq!(split_by(&overlapping, "::").estimate_tokens(), 22);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn interner_serde_roundtrip() {
+    let before = "a\nb\nc\nd\n";
+    let after = "a\nx\nc\ny\n";
+    let input = InternedInput::new(before, after);
+    let json = serde_json::to_string(&input.interner).unwrap();
+    assert_eq!(json, r#"["a","b","c","d","x","y"]"#);
+    let tokens = serde_json::to_string(&input.after).unwrap();
+    assert_eq!(tokens, "[0,4,2,5]");
+
+    // simulate a restart, only the serialized data is kept
+    let mut interner: Interner<String> = serde_json::from_str(&json).unwrap();
+    let after: Vec<Token> = serde_json::from_str(&tokens).unwrap();
+    assert_eq!(interner.num_tokens(), input.interner.num_tokens());
+    for token in input.before.iter().chain(&input.after) {
+        assert_eq!(interner[*token], input.interner[*token]);
+    }
+    let before: Vec<_> = lines(before)
+        .map(|line| interner.intern(line.to_owned()))
+        .collect();
+    assert_eq!(before, input.before);
+    assert_eq!(interner.num_tokens(), input.interner.num_tokens());
+
+    let mut changes = Vec::new();
+    diff_with_tokens(
+        Algorithm::Histogram,
+        &before,
+        &after,
+        interner.num_tokens(),
+        |before, after| changes.push((before, after)),
+    );
+    assert_eq!(changes, self::changes(Algorithm::Histogram, &input));
+
+    assert!(serde_json::from_str::<Interner<String>>(r#"["a","b","a"]"#).is_err());
+}
+
+#[test]
+fn udiff_change_at_end_of_file() {
+    let before = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+
+    // the last lines are removed, the after side ends before the before side
+    let input = InternedInput::new(before, "1\n2\n3\n4\n5\n6\n7\n8\n");
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let diff = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
+        expect![[r#"
+            @@ -6,5 +6,3 @@
+             6
+             7
+             8
+            -9
+            -10
+        "#]]
+        .assert_eq(&diff);
+    }
+
+    // lines are appended, the after side ends after the before side
+    let input = InternedInput::new(before, "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n");
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let diff = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
+        expect![[r#"
+            @@ -8,3 +8,5 @@
+             8
+             9
+             10
+            +11
+            +12
+        "#]]
+        .assert_eq(&diff);
+    }
+
+    // a change with a different length on both sides right before the last line
+    let input = InternedInput::new(before, "1\n2\n3\n4\n5\n6\n7\n8\nx\ny\nz\n10\n");
+    for algorithm in Algorithm::ALL {
+        println!("{algorithm:?}");
+        let diff = diff(algorithm, &input, UnifiedDiffBuilder::new(&input));
+        expect![[r#"
+            @@ -6,5 +6,7 @@
+             6
+             7
+             8
+            -9
+            +x
+            +y
+            +z
+             10
+        "#]]
+        .assert_eq(&diff);
+    }
+}
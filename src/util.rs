@@ -1,5 +1,23 @@
+//! Utilities for working with slices of interned [tokens](crate::intern::Token).
+//!
+//! These are the building blocks used by the diff [algorithms](crate::Algorithm)
+//! and can be useful when implementing custom diff drivers.
+
+use std::ops::Range;
+
+use hashbrown::HashMap;
+
 use crate::intern::Token;
 
+/// Returns the number of tokens at the start of `file1` and `file2` that are equal.
+///
+/// ```
+/// use imara_diff::intern::InternedInput;
+/// use imara_diff::util::common_prefix;
+///
+/// let input = InternedInput::new("a\nb\nc\n", "a\nb\nd\n");
+/// assert_eq!(common_prefix(&input.before, &input.after), 2);
+/// ```
 pub fn common_prefix(file1: &[Token], file2: &[Token]) -> u32 {
     let mut off = 0;
     for (token1, token2) in file1.iter().zip(file2) {
@@ -11,6 +29,15 @@ pub fn common_prefix(file1: &[Token], file2: &[Token]) -> u32 {
     off
 }
 
+/// Returns the number of tokens at the end of `file1` and `file2` that are equal.
+///
+/// ```
+/// use imara_diff::intern::InternedInput;
+/// use imara_diff::util::common_postfix;
+///
+/// let input = InternedInput::new("a\nb\nc\n", "d\nb\nc\n");
+/// assert_eq!(common_postfix(&input.before, &input.after), 2);
+/// ```
 pub fn common_postfix(file1: &[Token], file2: &[Token]) -> u32 {
     let mut off = 0;
     for (token1, token2) in file1.iter().rev().zip(file2.iter().rev()) {
@@ -22,27 +49,143 @@ pub fn common_postfix(file1: &[Token], file2: &[Token]) -> u32 {
     off
 }
 
+/// Returns the length of the common prefix and common postfix of `file1` and `file2`.
+///
+/// The postfix is computed after removing the prefix,
+/// so both never overlap even if one file is a subsequence of the other.
+///
+/// ```
+/// use imara_diff::intern::InternedInput;
+/// use imara_diff::util::common_edges;
+///
+/// let input = InternedInput::new("a\nb\nc\n", "a\nx\nc\n");
+/// assert_eq!(common_edges(&input.before, &input.after), (1, 1));
+///
+/// let input = InternedInput::new("a\na\n", "a\n");
+/// assert_eq!(common_edges(&input.before, &input.after), (1, 0));
+/// ```
 pub fn common_edges(file1: &[Token], file2: &[Token]) -> (u32, u32) {
     let prefix = common_prefix(file1, file2);
     let postfix = common_postfix(&file1[prefix as usize..], &file2[prefix as usize..]);
     (prefix, postfix)
 }
 
-pub fn strip_common_prefix(file1: &mut &[Token], file2: &mut &[Token]) -> u32 {
+/// Finds the longest contiguous sequence of tokens that occurs in both `before` and `after`.
+///
+/// Returns the position of that block in `before` and `after` or `None` if the two files
+/// do not share any token. If there are multiple blocks of the same length, the one
+/// that starts first in `after` is returned.
+/// All tokens should be smaller than `num_tokens` (just like for [`diff_with_tokens`](crate::diff_with_tokens)).
+///
+/// Unlike a diff this only finds a single common *substring*, which makes
+/// it useful as a primitive for move detection.
+/// Note that the runtime is proportional to the number of positions
+/// where the same token occurs in both files, which can be quadratic for very repetitive input.
+///
+/// ```
+/// use imara_diff::intern::InternedInput;
+/// use imara_diff::util::largest_common_block;
+///
+/// let input = InternedInput::new("a\nb\nc\nd\ne\n", "x\nc\nd\ne\na\nb\n");
+/// assert_eq!(
+///     largest_common_block(&input.before, &input.after, input.interner.num_tokens()),
+///     Some((2..5, 1..4))
+/// );
+/// ```
+pub fn largest_common_block(
+    before: &[Token],
+    after: &[Token],
+    num_tokens: u32,
+) -> Option<(Range<u32>, Range<u32>)> {
+    if before
+        .iter()
+        .chain(after)
+        .any(|token| token.0 >= num_tokens)
+    {
+        let (before, after, num_tokens) = renumber(before, after);
+        return largest_common_block(&before, &after, num_tokens);
+    }
+
+    // group the positions of all tokens in `before` by token id (a counting sort),
+    // the occurrences of `token` are `positions[starts[token]..starts[token + 1]]`
+    let mut starts = vec![0u32; num_tokens as usize + 1];
+    for token in before {
+        starts[token.0 as usize] += 1;
+    }
+    let mut end = 0;
+    for start in &mut starts {
+        end += *start;
+        *start = end;
+    }
+    let mut positions = vec![0u32; before.len()];
+    for (pos, token) in before.iter().enumerate().rev() {
+        let start = &mut starts[token.0 as usize];
+        *start -= 1;
+        positions[*start as usize] = pos as u32;
+    }
+
+    let mut best: Option<(u32, u32, u32)> = None;
+    for (after_pos, token) in after.iter().enumerate() {
+        let token = token.0 as usize;
+        let occurrences = &positions[starts[token] as usize..starts[token + 1] as usize];
+        for &before_pos in occurrences {
+            let before_pos = before_pos as usize;
+            // only extend from the start of a common block
+            if before_pos != 0 && after_pos != 0 && before[before_pos - 1] == after[after_pos - 1] {
+                continue;
+            }
+            let len = common_prefix(&before[before_pos..], &after[after_pos..]);
+            if best.map_or(true, |(_, _, best_len)| len > best_len) {
+                best = Some((before_pos as u32, after_pos as u32, len));
+            }
+        }
+    }
+    best.map(|(before_start, after_start, len)| {
+        (
+            before_start..before_start + len,
+            after_start..after_start + len,
+        )
+    })
+}
+
+/// Maps the tokens in `before` and `after` to consecutive ids starting at 0.
+/// Returns the renumbered files and the number of distinct tokens.
+pub(crate) fn renumber(before: &[Token], after: &[Token]) -> (Vec<Token>, Vec<Token>, u32) {
+    let mut ids = HashMap::new();
+    let mut renumber_file = |file: &[Token]| -> Vec<Token> {
+        file.iter()
+            .map(|&token| {
+                let next_id = Token(ids.len() as u32);
+                *ids.entry(token).or_insert(next_id)
+            })
+            .collect()
+    };
+    let before = renumber_file(before);
+    let after = renumber_file(after);
+    (before, after, ids.len() as u32)
+}
+
+pub(crate) fn strip_common_prefix(file1: &mut &[Token], file2: &mut &[Token]) -> u32 {
     let off = common_prefix(file1, file2);
     *file1 = &file1[off as usize..];
     *file2 = &file2[off as usize..];
     off
 }
 
-pub fn strip_common_postfix(file1: &mut &[Token], file2: &mut &[Token]) -> u32 {
+pub(crate) fn strip_common_postfix(file1: &mut &[Token], file2: &mut &[Token]) -> u32 {
     let off = common_postfix(file1, file2);
     *file1 = &file1[..file1.len() - off as usize];
     *file2 = &file2[..file2.len() - off as usize];
     off
 }
 
-pub fn sqrt(val: usize) -> u32 {
+/// A fast approximation of the square root of `val` that is used for heuristic cost limits.
+///
+/// This is not an integer square root. The result is the power of two `1 << (bits / 2)`,
+/// where `bits` is the number of significant bits in `val`. For example
+/// `sqrt(16) == 4`, `sqrt(17) == 4` and `sqrt(100) == 8`.
+/// The result is never 0 (`sqrt(0) == sqrt(1) == 1`) and saturates at `u32::MAX`.
+pub(crate) fn sqrt(val: usize) -> u32 {
     let nbits = (usize::BITS - val.leading_zeros()) / 2;
-    1 << nbits
+    1u32.checked_shl(nbits).unwrap_or(u32::MAX)
 }
@@ -0,0 +1,67 @@
+use std::hash::Hash;
+
+use crate::intern::{InternedInput, Interner, Token, TokenSource};
+use crate::{diff, Algorithm, Sink};
+
+/// Repeatedly diffs a growing file against a fixed reference file.
+///
+/// This is useful for continuously comparing something like a log that is
+/// only ever appended to against a known reference.
+/// The reference (`before`) is only tokenized and interned once.
+/// New tokens are interned as they are [`append`](crate::WindowedDiff::append)ed
+/// and the tokens that were already seen are never interned again.
+pub struct WindowedDiff<T> {
+    input: InternedInput<T>,
+    reference_tokens: u32,
+}
+
+impl<T: Eq + Hash> WindowedDiff<T> {
+    /// Create a `WindowedDiff` that compares against `before`.
+    /// Initially the `after` file is empty.
+    pub fn new<I: TokenSource<Token = T>>(before: I) -> Self {
+        let token_estimate = before.estimate_tokens() as usize;
+        let mut input = InternedInput {
+            before: Vec::with_capacity(token_estimate),
+            after: Vec::new(),
+            interner: Interner::new(token_estimate),
+        };
+        input.update_before(before.tokenize());
+        let reference_tokens = input.interner.num_tokens();
+        Self {
+            input,
+            reference_tokens,
+        }
+    }
+
+    /// Interns the tokens yielded by `input` and appends them to the `after` file.
+    pub fn append(&mut self, input: impl Iterator<Item = T>) {
+        let interner = &mut self.input.interner;
+        self.input
+            .after
+            .extend(input.map(|token| interner.intern(token)));
+    }
+
+    /// Empties the `after` file and erases all tokens that only occurred in it
+    /// from the interner (see [`erase_tokens_after`](crate::intern::Interner::erase_tokens_after)).
+    /// This relies on new tokens receiving increasing ids, which is why a `WindowedDiff`
+    /// always uses an interner without a [token limit](crate::intern::Interner::with_max_tokens).
+    pub fn reset(&mut self) {
+        self.input.after.clear();
+        self.input
+            .interner
+            .erase_tokens_after(Token(self.reference_tokens));
+    }
+}
+
+impl<T> WindowedDiff<T> {
+    /// The interned reference and the `after` file accumulated so far.
+    pub fn input(&self) -> &InternedInput<T> {
+        &self.input
+    }
+
+    /// Computes an edit-script that transforms the reference into the
+    /// `after` file accumulated so far, see [`diff`].
+    pub fn diff<S: Sink>(&self, algorithm: Algorithm, sink: S) -> S::Out {
+        diff(algorithm, &self.input, sink)
+    }
+}